@@ -0,0 +1,53 @@
+//! Error type for pcap parsing and detruncation.
+
+use std::fmt;
+use std::io;
+
+/// Errors produced while reading, detruncating, or writing a capture.
+#[derive(Debug)]
+pub enum PcapError {
+    /// An underlying I/O error.
+    Io(io::Error),
+    /// A record claimed more captured bytes than it had on the wire.
+    CaptureExceedsOriginal { caplen: u32, origlen: u32 },
+    /// A record's captured length exceeded the header `snaplen`.
+    LengthExceedsSnaplen { len: usize, snaplen: usize },
+    /// A record's length exceeded the configured per-record allocation ceiling.
+    RecordTooLarge { len: usize, limit: usize },
+    /// A block or record was structurally invalid.
+    Malformed(&'static str),
+}
+
+impl fmt::Display for PcapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PcapError::Io(e) => write!(f, "I/O error: {e}"),
+            PcapError::CaptureExceedsOriginal { caplen, origlen } => write!(
+                f,
+                "captured length {caplen} is greater than original length {origlen}"
+            ),
+            PcapError::LengthExceedsSnaplen { len, snaplen } => {
+                write!(f, "record length {len} exceeds snaplen {snaplen}")
+            }
+            PcapError::RecordTooLarge { len, limit } => {
+                write!(f, "record length {len} exceeds allocation limit {limit}")
+            }
+            PcapError::Malformed(what) => write!(f, "malformed capture: {what}"),
+        }
+    }
+}
+
+impl std::error::Error for PcapError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PcapError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for PcapError {
+    fn from(e: io::Error) -> Self {
+        PcapError::Io(e)
+    }
+}
@@ -0,0 +1,54 @@
+//! `dtrunc` reads, detruncates, and writes libpcap captures.
+//!
+//! The format types live in [`record`], streaming I/O in [`reader`] and
+//! [`writer`], and the padding transform is exposed as [`detruncate`] so it can
+//! be used independently of any file handling.
+
+use deku::bitvec::{BitSlice, BitVec, Msb0};
+use deku::ctx::Endian;
+use deku::prelude::*;
+
+pub mod error;
+pub mod pcapng;
+pub mod reader;
+pub mod record;
+pub mod writer;
+
+pub use error::PcapError;
+pub use pcapng::{detruncate_block, is_pcapng, PcapngReader};
+pub use reader::PcapReader;
+pub use record::{detruncate, CapturedPacket, Encoding, PcapHeader, PcapRecord};
+pub use writer::PcapWriter;
+
+/// Length of the fixed libpcap file header.
+pub const PCAP_HEADER_LEN: usize = 24;
+/// Length of the fixed per-record header preceding each packet's payload.
+pub const PCAP_RECORD_HEADER_LEN: usize = 16;
+/// Default per-record allocation ceiling (1.5 GiB) used as DOS protection
+/// against hostile length fields.
+pub const DEFAULT_SNAPLEN_CEILING: usize = 1536 * 1024 * 1024;
+/// Microsecond-resolution magic, as decoded in the capture's own byte order.
+pub(crate) const PCAP_MAGIC_US: u32 = 0xa1b2c3d4;
+/// Nanosecond-resolution magic, as decoded in the capture's own byte order.
+pub(crate) const PCAP_MAGIC_NS: u32 = 0xa1b23c4d;
+
+/// Decode a deku type with an explicit byte order, returning the number of
+/// bytes consumed alongside the value.
+pub(crate) fn read_ctx<'a, T>(input: &'a [u8], endian: Endian) -> Option<(usize, T)>
+where
+    T: DekuRead<'a, Endian>,
+{
+    let bits = BitSlice::<u8, Msb0>::from_slice(input);
+    let (rest, value) = T::read(bits, endian).ok()?;
+    Some(((bits.len() - rest.len()) / 8, value))
+}
+
+/// Encode a deku type with an explicit byte order.
+pub(crate) fn write_ctx<T>(value: &T, endian: Endian) -> Vec<u8>
+where
+    T: DekuWrite<Endian>,
+{
+    let mut out = BitVec::<u8, Msb0>::new();
+    value.write(&mut out, endian).unwrap();
+    out.into_vec()
+}
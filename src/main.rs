@@ -1,8 +1,13 @@
 use clap::Parser;
-use deku::prelude::*;
 use std::fs::File;
-use std::io;
-use std::io::{Read, Write};
+use std::io::{self, Read, Write};
+use std::process::ExitCode;
+
+use dtrunc::reader::read_full_prefix;
+use dtrunc::{
+    detruncate, detruncate_block, is_pcapng, PcapError, PcapReader, PcapWriter, PcapngReader,
+    PCAP_HEADER_LEN,
+};
 
 #[derive(Parser, Debug)]
 struct Opt {
@@ -19,163 +24,115 @@ struct Opt {
     verbose: bool,
 }
 
-const PCAP_HEADER_LEN: usize = 24;
-const PCAP_MAGIC: u32 = 0xa1b2c3d4;
-
-#[derive(Debug, PartialEq, DekuRead, DekuWrite)]
-#[deku(endian = "little")]
-struct PcapHeader {
-    magic: u32,
-    major: u16,
-    minor: u16,
-    resv1: u32,
-    resv2: u32,
-    snaplen: u32,
-    #[deku(bits = "3")]
-    fcs: u8,
-    #[deku(bits = "1")]
-    f: u8,
-    #[deku(bits = "28")]
-    linktype: u32,
-}
+fn run(opt: &Opt) -> Result<(), PcapError> {
+    let mut file = io::BufReader::new(File::open(&opt.input)?);
 
-impl PcapHeader {
-    fn read(reader: &[u8]) -> Option<Self> {
-        let (_, header) = PcapHeader::from_bytes((reader, 0)).ok()?;
-        if header.magic == PCAP_MAGIC {
-            Some(header)
-        } else {
-            None
-        }
+    // Peek the leading bytes to pick the format, then replay them so the
+    // chosen reader sees a complete stream.
+    let mut prefix = [0u8; 4];
+    if !read_full_prefix(&mut file, &mut prefix)? {
+        println!("Error: {} cannot be loaded as a pcap file", opt.input);
+        return Ok(());
     }
+    let reader = io::Cursor::new(prefix).chain(file);
 
-    fn out(&self) -> Vec<u8> {
-        self.to_bytes().unwrap()
+    if is_pcapng(&prefix) {
+        run_pcapng(opt, reader)
+    } else {
+        run_pcap(opt, reader)
     }
 }
 
-#[derive(Debug, PartialEq, DekuRead, DekuWrite)]
-#[deku(endian = "little")]
-struct PcapRecord {
-    ts: u32,
-    tn: u32,
-    caplen: u32,
-    origlen: u32,
-    #[deku(count = "caplen")]
-    data: Vec<u8>,
-}
-
-impl PcapRecord {
-    fn read_all(mut cursor: &[u8]) -> Vec<Self> {
-        let mut records = Vec::<Self>::new();
-        while let Some(record) = Self::read(cursor) {
-            cursor = &cursor[record.len()..];
-            records.push(record);
-        }
-        records
-    }
-
-    fn read(reader: &[u8]) -> Option<Self> {
-        let (_, record) = PcapRecord::from_bytes((reader, 0)).ok()?;
-        Some(record)
-    }
+fn run_pcap<R: Read>(opt: &Opt, reader: R) -> Result<(), PcapError> {
+    let Some((header, mut reader)) = PcapReader::new(reader)? else {
+        println!("Error: {} cannot be loaded as a pcap file", opt.input);
+        return Ok(());
+    };
 
-    fn write_all(records: &[Self], opt: &Opt) -> Vec<u8> {
-        let mut out = Vec::<u8>::new();
-        for (n, r) in records.iter().enumerate() {
-            if opt.verbose {
-                print!("\rAppending {:04} of {:04}", n + 1, records.len());
-                let _ = io::stdout().flush();
-            }
-            out.append(&mut r.out());
-        }
-        out
-    }
+    println!("Loading {}...", opt.input);
+    let enc = reader.encoding();
+    let limit = reader.limit();
 
-    fn out(&self) -> Vec<u8> {
-        self.to_bytes().unwrap()
-    }
+    let output = File::create(&opt.output)?;
+    let mut writer = PcapWriter::new(io::BufWriter::new(output), &header, enc)?;
 
-    fn len(&self) -> usize {
-        self.data.len() + 16
-    }
+    println!("Detruncating pcap records...");
+    let mut total_count = 0;
+    let mut count = 0;
+    let mut orig_size = PCAP_HEADER_LEN;
+    let mut out_size = PCAP_HEADER_LEN;
 
-    fn detruncate(records: Vec<PcapRecord>, opt: &Opt) -> Vec<PcapRecord> {
-        let mut out = Vec::<PcapRecord>::new();
-        let total_count = records.len();
-        let mut count = 0;
-        let mut orig_size = PCAP_HEADER_LEN;
-        let mut out_size = PCAP_HEADER_LEN;
-
-        for (n, mut rec) in records.into_iter().enumerate() {
-            if rec.caplen == rec.origlen {
-                if opt.verbose {
-                    orig_size += rec.len();
-                    out_size += rec.len();
-                }
-                out.push(rec);
-                continue;
-            }
-
-            if rec.caplen > rec.origlen {
-                panic!("Error: Captured length is greater than original length!");
-            }
+    while let Some(mut record) = reader.read_record()? {
+        total_count += 1;
 
+        orig_size += record.len();
+        if detruncate(&mut record, limit)? {
             if opt.verbose {
-                println!(
-                    "Packet {}: Resizing from {} to {}",
-                    n + 1,
-                    rec.caplen,
-                    rec.origlen
-                );
-                orig_size += rec.len();
-                out_size += rec.origlen as usize + 16;
+                println!("Packet {}: Resizing to {}", total_count, record.origlen);
             }
-            rec.data.resize(
-                rec.origlen
-                    .try_into()
-                    .expect("Error: Unable to convert origlen to usize"),
-                0x00,
-            );
-            rec.caplen = rec.origlen;
             count += 1;
-            out.push(rec);
         }
+        out_size += record.len();
 
         if opt.verbose {
-            println!("Packets detruncated: {count} of {total_count}");
-            println!("Original filesize: {orig_size} New filesize: {out_size}");
+            print!("\rAppending {total_count:04}");
+            let _ = io::stdout().flush();
         }
+        writer.write_record(&record)?;
+    }
 
-        out
+    if opt.verbose {
+        println!();
+        println!("Packets detruncated: {count} of {total_count}");
+        println!("Original filesize: {orig_size} New filesize: {out_size}");
     }
+
+    println!("Writing output to: {}", opt.output);
+    Ok(())
 }
 
-fn main() {
-    let opt = Opt::parse();
+fn run_pcapng<R: Read>(opt: &Opt, reader: R) -> Result<(), PcapError> {
+    let Some(mut reader) = PcapngReader::new(reader)? else {
+        println!("Error: {} cannot be loaded as a pcap file", opt.input);
+        return Ok(());
+    };
+
+    println!("Loading {}...", opt.input);
+    let endian = reader.endian();
+    let limit = reader.limit();
 
-    let mut file = File::open(&opt.input).expect("Error: Cannot open file");
-    let mut reader = Vec::<u8>::new();
-    let _ = file.read_to_end(&mut reader).expect("Cannot read file");
+    let mut output = io::BufWriter::new(File::create(&opt.output)?);
 
-    if let Some(header) = PcapHeader::read(&reader) {
-        println!("Loading {}...", opt.input);
+    println!("Detruncating pcapng blocks...");
+    let mut total_count = 0;
+    let mut count = 0;
 
-        let records = PcapRecord::read_all(&reader[PCAP_HEADER_LEN..]);
+    while let Some(mut block) = reader.read_block()? {
+        total_count += 1;
+        if detruncate_block(&mut block, endian, limit)? {
+            if opt.verbose {
+                println!("Block {total_count}: padded enhanced packet block");
+            }
+            count += 1;
+        }
+        output.write_all(&block)?;
+    }
 
-        println!("Detruncating pcap records...");
-        let detruncated = PcapRecord::detruncate(records, &opt);
+    if opt.verbose {
+        println!("Blocks detruncated: {count} of {total_count}");
+    }
 
-        println!("Preparing data to write...");
-        let mut data = header.out();
-        data.append(&mut PcapRecord::write_all(&detruncated, &opt));
+    println!("Writing output to: {}", opt.output);
+    Ok(())
+}
 
-        println!("Writing output to: {}", opt.output);
-        let mut output = File::create(&opt.output).expect("Error: Cannot create output file");
-        output
-            .write_all(&data)
-            .expect("Error writing to output file");
-    } else {
-        println!("Error: {} cannot be loaded as a pcap file", opt.input);
+fn main() -> ExitCode {
+    let opt = Opt::parse();
+    match run(&opt) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {e}");
+            ExitCode::FAILURE
+        }
     }
 }
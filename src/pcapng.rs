@@ -0,0 +1,271 @@
+//! Streaming pcapng parser with block-aware detruncation.
+//!
+//! pcapng files are a stream of length-prefixed blocks. Each block is
+//! `type (u32)`, `total length (u32)`, a body, and a trailing copy of the total
+//! length, all padded to 32-bit boundaries. The byte order of the whole section
+//! is learned from the Section Header Block's byte-order magic.
+
+use std::io::{self, Read};
+
+use deku::ctx::Endian;
+
+use crate::error::PcapError;
+use crate::reader::read_full;
+use crate::DEFAULT_SNAPLEN_CEILING;
+
+/// Leading bytes of a Section Header Block (`0x0a0d0d0a`); identical in either
+/// byte order, so they double as the pcapng format marker.
+pub const PCAPNG_SHB_TYPE: [u8; 4] = [0x0a, 0x0d, 0x0d, 0x0a];
+/// Byte-order magic (`0x1a2b3c4d`) as stored in a big-endian section.
+const BYTE_ORDER_MAGIC_BE: [u8; 4] = [0x1a, 0x2b, 0x3c, 0x4d];
+/// Byte-order magic (`0x1a2b3c4d`) as stored in a little-endian section.
+const BYTE_ORDER_MAGIC_LE: [u8; 4] = [0x4d, 0x3c, 0x2b, 0x1a];
+/// Enhanced Packet Block type.
+const EPB_TYPE: u32 = 0x0000_0006;
+
+const BLOCK_HEADER_LEN: usize = 8;
+/// Interface ID, timestamp high/low, captured length, original length.
+const EPB_BODY_PREFIX: usize = 20;
+
+/// `true` if the leading bytes mark a pcapng Section Header Block.
+pub fn is_pcapng(prefix: &[u8]) -> bool {
+    prefix.len() >= 4 && prefix[0..4] == PCAPNG_SHB_TYPE
+}
+
+fn rd_u32(endian: Endian, b: &[u8]) -> u32 {
+    let a = [b[0], b[1], b[2], b[3]];
+    match endian {
+        Endian::Big => u32::from_be_bytes(a),
+        _ => u32::from_le_bytes(a),
+    }
+}
+
+fn wr_u32(endian: Endian, v: u32) -> [u8; 4] {
+    match endian {
+        Endian::Big => v.to_be_bytes(),
+        _ => v.to_le_bytes(),
+    }
+}
+
+/// Round a length up to the next 32-bit boundary.
+fn pad4(n: usize) -> usize {
+    (n + 3) & !3
+}
+
+/// Streaming pcapng parser that yields one raw block at a time, keeping memory
+/// use bounded to a single block.
+pub struct PcapngReader<R: Read> {
+    reader: R,
+    endian: Endian,
+    limit: usize,
+    first: Option<Vec<u8>>,
+}
+
+impl<R: Read> PcapngReader<R> {
+    /// Read the Section Header Block to learn the section byte order, returning
+    /// a reader whose first [`read_block`](Self::read_block) yields that Section
+    /// Header Block, or `None` if the input is not a pcapng capture.
+    pub fn new(mut reader: R) -> Result<Option<Self>, PcapError> {
+        let mut head = [0u8; 12];
+        if !read_full(&mut reader, &mut head)? {
+            return Ok(None);
+        }
+        if head[0..4] != PCAPNG_SHB_TYPE {
+            return Ok(None);
+        }
+        let endian = if head[8..12] == BYTE_ORDER_MAGIC_BE {
+            Endian::Big
+        } else if head[8..12] == BYTE_ORDER_MAGIC_LE {
+            Endian::Little
+        } else {
+            return Ok(None);
+        };
+
+        let total_len = rd_u32(endian, &head[4..8]) as usize;
+        if total_len < 12 || !total_len.is_multiple_of(4) {
+            return Err(PcapError::Malformed("section header block length"));
+        }
+        if total_len > DEFAULT_SNAPLEN_CEILING {
+            return Err(PcapError::RecordTooLarge {
+                len: total_len,
+                limit: DEFAULT_SNAPLEN_CEILING,
+            });
+        }
+        let mut block = head.to_vec();
+        let mut rest = vec![0u8; total_len - head.len()];
+        if !read_full(&mut reader, &mut rest)? {
+            return Err(PcapError::Io(io::Error::from(io::ErrorKind::UnexpectedEof)));
+        }
+        block.extend_from_slice(&rest);
+
+        Ok(Some(PcapngReader {
+            reader,
+            endian,
+            limit: DEFAULT_SNAPLEN_CEILING,
+            first: Some(block),
+        }))
+    }
+
+    /// Override the per-block allocation ceiling (default
+    /// [`DEFAULT_SNAPLEN_CEILING`]).
+    pub fn with_limit(mut self, limit: usize) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    /// The per-block allocation ceiling currently in effect.
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    /// The byte order of the section being read.
+    pub fn endian(&self) -> Endian {
+        self.endian
+    }
+
+    /// Read the next block in full, or `None` at end of file.
+    pub fn read_block(&mut self) -> Result<Option<Vec<u8>>, PcapError> {
+        if let Some(block) = self.first.take() {
+            return Ok(Some(block));
+        }
+        let mut head = [0u8; BLOCK_HEADER_LEN];
+        if !read_full(&mut self.reader, &mut head)? {
+            return Ok(None);
+        }
+        let total_len = rd_u32(self.endian, &head[4..8]) as usize;
+        if total_len < 12 || !total_len.is_multiple_of(4) {
+            return Err(PcapError::Malformed("block total length"));
+        }
+        if total_len > self.limit {
+            return Err(PcapError::RecordTooLarge {
+                len: total_len,
+                limit: self.limit,
+            });
+        }
+        let mut block = Vec::with_capacity(total_len);
+        block.extend_from_slice(&head);
+        let mut rest = vec![0u8; total_len - BLOCK_HEADER_LEN];
+        if !read_full(&mut self.reader, &mut rest)? {
+            return Err(PcapError::Io(io::Error::from(io::ErrorKind::UnexpectedEof)));
+        }
+        block.extend_from_slice(&rest);
+        Ok(Some(block))
+    }
+}
+
+impl<R: Read> Iterator for PcapngReader<R> {
+    type Item = Result<Vec<u8>, PcapError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_block().transpose()
+    }
+}
+
+/// Detruncate a single pcapng block in place, returning whether it was padded.
+///
+/// Only Enhanced Packet Blocks are touched: their captured data is padded up to
+/// the original on-wire length and the block's length fields are corrected. All
+/// other block types (Interface Description, Section Header, …) pass through
+/// untouched.
+pub fn detruncate_block(
+    block: &mut Vec<u8>,
+    endian: Endian,
+    limit: usize,
+) -> Result<bool, PcapError> {
+    if block.len() < BLOCK_HEADER_LEN + EPB_BODY_PREFIX + 4 {
+        return Ok(false);
+    }
+    if rd_u32(endian, &block[0..4]) != EPB_TYPE {
+        return Ok(false);
+    }
+
+    let caplen = rd_u32(endian, &block[20..24]);
+    let origlen = rd_u32(endian, &block[24..28]);
+    if caplen > origlen {
+        return Err(PcapError::CaptureExceedsOriginal { caplen, origlen });
+    }
+    let origlen_sz = origlen as usize;
+    if origlen_sz > limit {
+        return Err(PcapError::RecordTooLarge {
+            len: origlen_sz,
+            limit,
+        });
+    }
+    if caplen == origlen {
+        return Ok(false);
+    }
+
+    let old_pad = pad4(caplen as usize);
+    let new_pad = pad4(origlen_sz);
+    let delta = new_pad - old_pad;
+
+    let data_start = BLOCK_HEADER_LEN + EPB_BODY_PREFIX;
+    let opts_start = data_start + old_pad;
+    if block.len() < opts_start + 4 {
+        return Err(PcapError::Malformed("enhanced packet block length"));
+    }
+
+    // Promote the captured length to the original length and grow the padded
+    // data region by the extra zero bytes it now needs.
+    block[20..24].copy_from_slice(&wr_u32(endian, origlen));
+    block.splice(opts_start..opts_start, vec![0u8; delta]);
+
+    // Fix the leading and trailing total-length fields.
+    let new_total = wr_u32(endian, block.len() as u32);
+    block[4..8].copy_from_slice(&new_total);
+    let end = block.len();
+    block[end - 4..end].copy_from_slice(&new_total);
+
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn le(v: u32) -> [u8; 4] {
+        v.to_le_bytes()
+    }
+
+    #[test]
+    fn epb_pad_up_fixes_total_length() {
+        let endian = Endian::Little;
+        let mut block = Vec::new();
+        block.extend_from_slice(&le(EPB_TYPE));
+        block.extend_from_slice(&le(36)); // block total length
+        block.extend_from_slice(&le(0)); // interface id
+        block.extend_from_slice(&le(0)); // timestamp high
+        block.extend_from_slice(&le(0)); // timestamp low
+        block.extend_from_slice(&le(4)); // captured length
+        block.extend_from_slice(&le(8)); // original length
+        block.extend_from_slice(&[0xAA, 0xBB, 0xCC, 0xDD]); // 4 captured bytes (pad4(4) == 4)
+        block.extend_from_slice(&le(36)); // trailing total length
+        assert_eq!(block.len(), 36);
+
+        assert!(detruncate_block(&mut block, endian, DEFAULT_SNAPLEN_CEILING).unwrap());
+        assert_eq!(block.len(), 40);
+        // captured length promoted to the original length
+        assert_eq!(rd_u32(endian, &block[20..24]), 8);
+        // leading and trailing total-length fields agree and match the new size
+        assert_eq!(rd_u32(endian, &block[4..8]), 40);
+        let end = block.len();
+        assert_eq!(rd_u32(endian, &block[end - 4..end]), 40);
+        // captured bytes preserved, padding zeroed
+        assert_eq!(&block[28..32], &[0xAA, 0xBB, 0xCC, 0xDD]);
+        assert_eq!(&block[32..36], &[0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn non_epb_block_untouched() {
+        let endian = Endian::Little;
+        // An Interface Description Block (type 1) must pass through verbatim.
+        let mut block = Vec::new();
+        block.extend_from_slice(&le(0x0000_0001));
+        block.extend_from_slice(&le(36));
+        block.extend_from_slice(&[0u8; 24]);
+        block.extend_from_slice(&le(36));
+        let before = block.clone();
+        assert!(!detruncate_block(&mut block, endian, DEFAULT_SNAPLEN_CEILING).unwrap());
+        assert_eq!(block, before);
+    }
+}
@@ -0,0 +1,247 @@
+//! Streaming libpcap parser.
+
+use std::io::{self, Read};
+
+use crate::error::PcapError;
+use crate::record::{Encoding, PcapHeader, PcapRecord};
+use crate::{DEFAULT_SNAPLEN_CEILING, PCAP_HEADER_LEN, PCAP_RECORD_HEADER_LEN};
+
+/// Read a fixed-size prefix from `reader`, returning `false` on a clean
+/// end-of-stream (used to peek the format marker before dispatching).
+pub fn read_full_prefix<R: Read>(reader: &mut R, buf: &mut [u8]) -> io::Result<bool> {
+    read_full(reader, buf)
+}
+
+/// Read exactly `buf.len()` bytes, reporting a clean end-of-stream (no bytes
+/// read at all) as `Ok(false)` so the record iterator can stop gracefully.
+pub(crate) fn read_full<R: Read>(reader: &mut R, buf: &mut [u8]) -> io::Result<bool> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) => {
+                if filled == 0 {
+                    return Ok(false);
+                }
+                return Err(io::Error::from(io::ErrorKind::UnexpectedEof));
+            }
+            Ok(n) => filled += n,
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(true)
+}
+
+/// Streaming pcap parser that decodes one [`PcapRecord`] at a time, reusing an
+/// internal payload buffer so memory use stays bounded regardless of the
+/// capture size.
+pub struct PcapReader<R: Read> {
+    reader: R,
+    enc: Encoding,
+    snaplen: usize,
+    limit: usize,
+    buf: Vec<u8>,
+}
+
+impl<R: Read> PcapReader<R> {
+    /// Consume the file header from `reader`, returning it together with a
+    /// streaming reader for the remaining records, or `None` if the input is
+    /// not a libpcap capture.
+    pub fn new(mut reader: R) -> Result<Option<(PcapHeader, Self)>, PcapError> {
+        let mut head = [0u8; PCAP_HEADER_LEN];
+        if !read_full(&mut reader, &mut head)? {
+            return Ok(None);
+        }
+        let Some((header, enc)) = PcapHeader::read(&head) else {
+            return Ok(None);
+        };
+        let snaplen = header.snaplen as usize;
+        let this = PcapReader {
+            reader,
+            enc,
+            snaplen,
+            limit: DEFAULT_SNAPLEN_CEILING,
+            buf: Vec::with_capacity(snaplen.min(64 * 1024)),
+        };
+        Ok(Some((header, this)))
+    }
+
+    /// Override the per-record allocation ceiling (default
+    /// [`DEFAULT_SNAPLEN_CEILING`]).
+    pub fn with_limit(mut self, limit: usize) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    /// The per-record allocation ceiling currently in effect.
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    /// The byte order and timestamp resolution of the capture being read.
+    pub fn encoding(&self) -> Encoding {
+        self.enc
+    }
+
+    /// Parse the next record, or `None` at end of file. Records whose captured
+    /// length exceeds their original length or the header `snaplen`, or whose
+    /// original length exceeds the allocation ceiling, are rejected before any
+    /// payload is buffered.
+    pub fn read_record(&mut self) -> Result<Option<PcapRecord>, PcapError> {
+        let mut head = [0u8; PCAP_RECORD_HEADER_LEN];
+        if !read_full(&mut self.reader, &mut head)? {
+            return Ok(None);
+        }
+        let ts = self.enc.u32(head[0..4].try_into().unwrap());
+        let tn = self.enc.u32(head[4..8].try_into().unwrap());
+        let caplen = self.enc.u32(head[8..12].try_into().unwrap());
+        let origlen = self.enc.u32(head[12..16].try_into().unwrap());
+
+        if caplen > origlen {
+            return Err(PcapError::CaptureExceedsOriginal { caplen, origlen });
+        }
+        let count = caplen as usize;
+        let orig = origlen as usize;
+        // Only the *captured* bytes are bounded by snaplen; `origlen` legitimately
+        // exceeds it for a record that was truncated at capture time, which is
+        // precisely what detruncation restores. The `limit` ceiling guards the
+        // allocation against hostile lengths.
+        if count > self.snaplen {
+            return Err(PcapError::LengthExceedsSnaplen {
+                len: count,
+                snaplen: self.snaplen,
+            });
+        }
+        if orig > self.limit {
+            return Err(PcapError::RecordTooLarge {
+                len: orig,
+                limit: self.limit,
+            });
+        }
+
+        self.buf.resize(count, 0x00);
+        if !read_full(&mut self.reader, &mut self.buf)? {
+            return Err(PcapError::Io(io::Error::from(io::ErrorKind::UnexpectedEof)));
+        }
+        Ok(Some(PcapRecord {
+            ts,
+            tn,
+            caplen,
+            origlen,
+            data: self.buf[..count].to_vec(),
+        }))
+    }
+}
+
+impl<R: Read> Iterator for PcapReader<R> {
+    type Item = Result<PcapRecord, PcapError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_record().transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::record::{Encoding, PcapHeader, PcapRecord};
+    use crate::{detruncate, PCAP_MAGIC_NS, PCAP_MAGIC_US};
+    use deku::ctx::Endian;
+    use std::io::Cursor;
+
+    fn header(magic: u32, snaplen: u32) -> PcapHeader {
+        PcapHeader {
+            magic,
+            major: 2,
+            minor: 4,
+            resv1: 0,
+            resv2: 0,
+            snaplen,
+            fcs: 0,
+            f: 0,
+            linktype: 1,
+        }
+    }
+
+    fn roundtrip(magic: u32, endian: Endian, nanos: bool) {
+        let enc = Encoding { endian, nanos };
+        let hdr = header(magic, 262_144);
+        let rec = PcapRecord {
+            ts: 0x1122_3344,
+            tn: 0x5566_7788,
+            caplen: 4,
+            origlen: 4,
+            data: vec![1, 2, 3, 4],
+        };
+        let mut bytes = hdr.out(enc);
+        bytes.extend(rec.out(enc));
+
+        let (rhdr, mut reader) = PcapReader::new(Cursor::new(bytes.clone())).unwrap().unwrap();
+        assert_eq!(reader.encoding(), enc);
+        let rrec = reader.read_record().unwrap().unwrap();
+        assert_eq!(rrec, rec);
+        assert!(reader.read_record().unwrap().is_none());
+
+        let mut out = rhdr.out(enc);
+        out.extend(rrec.out(enc));
+        assert_eq!(out, bytes, "round-trip must be byte-for-byte lossless");
+    }
+
+    #[test]
+    fn big_endian_micros_roundtrip() {
+        roundtrip(PCAP_MAGIC_US, Endian::Big, false);
+    }
+
+    #[test]
+    fn little_endian_nanos_roundtrip() {
+        roundtrip(PCAP_MAGIC_NS, Endian::Little, true);
+    }
+
+    #[test]
+    fn truncated_record_over_snaplen_is_detruncated() {
+        let enc = Encoding {
+            endian: Endian::Little,
+            nanos: false,
+        };
+        let hdr = header(PCAP_MAGIC_US, 96);
+        let rec = PcapRecord {
+            ts: 1,
+            tn: 2,
+            caplen: 96,
+            origlen: 1500,
+            data: vec![0x41; 96],
+        };
+        let mut bytes = hdr.out(enc);
+        bytes.extend(rec.out(enc));
+
+        let (_h, mut reader) = PcapReader::new(Cursor::new(bytes)).unwrap().unwrap();
+        let limit = reader.limit();
+        let mut got = reader.read_record().unwrap().unwrap();
+        assert!(detruncate(&mut got, limit).unwrap());
+        assert_eq!(got.caplen, 1500);
+        assert_eq!(got.data.len(), 1500);
+    }
+
+    #[test]
+    fn captured_over_snaplen_is_rejected() {
+        let enc = Encoding {
+            endian: Endian::Little,
+            nanos: false,
+        };
+        let hdr = header(PCAP_MAGIC_US, 96);
+        let rec = PcapRecord {
+            ts: 1,
+            tn: 2,
+            caplen: 200,
+            origlen: 200,
+            data: vec![0; 200],
+        };
+        let mut bytes = hdr.out(enc);
+        bytes.extend(rec.out(enc));
+        let (_h, mut reader) = PcapReader::new(Cursor::new(bytes)).unwrap().unwrap();
+        assert!(matches!(
+            reader.read_record(),
+            Err(PcapError::LengthExceedsSnaplen { .. })
+        ));
+    }
+}
@@ -0,0 +1,209 @@
+//! The libpcap file header and per-packet record types.
+
+use deku::ctx::Endian;
+use deku::prelude::*;
+
+use crate::error::PcapError;
+use crate::{read_ctx, write_ctx, PCAP_MAGIC_NS, PCAP_MAGIC_US, PCAP_RECORD_HEADER_LEN};
+
+/// Byte order and timestamp resolution detected from the file magic. A capture
+/// is read and re-emitted with the same encoding so round-tripping is lossless.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Encoding {
+    /// Byte order of every multi-byte field in the file.
+    pub endian: Endian,
+    /// `true` when record timestamps are nanosecond rather than microsecond
+    /// fractions.
+    pub nanos: bool,
+}
+
+impl Encoding {
+    /// Decode a 4-byte field in this capture's byte order.
+    pub(crate) fn u32(&self, bytes: [u8; 4]) -> u32 {
+        match self.endian {
+            Endian::Big => u32::from_be_bytes(bytes),
+            _ => u32::from_le_bytes(bytes),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, DekuRead, DekuWrite)]
+#[deku(ctx = "endian: Endian", endian = "endian")]
+pub struct PcapHeader {
+    pub magic: u32,
+    pub major: u16,
+    pub minor: u16,
+    pub resv1: u32,
+    pub resv2: u32,
+    pub snaplen: u32,
+    #[deku(bits = "3")]
+    pub fcs: u8,
+    #[deku(bits = "1")]
+    pub f: u8,
+    #[deku(bits = "28")]
+    pub linktype: u32,
+}
+
+impl PcapHeader {
+    /// Parse a file header, returning it together with the [`Encoding`] implied
+    /// by its magic, or `None` if the bytes are not a libpcap header.
+    pub fn read(reader: &[u8]) -> Option<(Self, Encoding)> {
+        if reader.len() < 4 {
+            return None;
+        }
+        // The magic is stored in the writer's byte order; reading the raw bytes
+        // as little-endian distinguishes all four libpcap variants at once.
+        let raw = u32::from_le_bytes(reader[0..4].try_into().ok()?);
+        let (endian, nanos) = match raw {
+            0xa1b2c3d4 => (Endian::Little, false),
+            0xd4c3b2a1 => (Endian::Big, false),
+            0xa1b23c4d => (Endian::Little, true),
+            0x4d3cb2a1 => (Endian::Big, true),
+            _ => return None,
+        };
+        let (_, header): (usize, PcapHeader) = read_ctx(reader, endian)?;
+        if header.magic == PCAP_MAGIC_US || header.magic == PCAP_MAGIC_NS {
+            Some((header, Encoding { endian, nanos }))
+        } else {
+            None
+        }
+    }
+
+    /// Serialize the header in the given encoding's byte order.
+    pub fn out(&self, enc: Encoding) -> Vec<u8> {
+        write_ctx(self, enc.endian)
+    }
+}
+
+#[derive(Debug, PartialEq, DekuRead, DekuWrite)]
+#[deku(ctx = "endian: Endian", endian = "endian")]
+pub struct PcapRecord {
+    pub ts: u32,
+    pub tn: u32,
+    pub caplen: u32,
+    pub origlen: u32,
+    #[deku(count = "caplen")]
+    pub data: Vec<u8>,
+}
+
+impl PcapRecord {
+    /// Serialize the record in the given encoding's byte order.
+    pub fn out(&self, enc: Encoding) -> Vec<u8> {
+        write_ctx(self, enc.endian)
+    }
+
+    /// Total on-disk size of the record, header plus payload.
+    pub fn len(&self) -> usize {
+        self.data.len() + PCAP_RECORD_HEADER_LEN
+    }
+
+    /// `true` when the record carries no captured bytes.
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// A borrowed, format-agnostic view of the packet: its timestamp, captured
+    /// bytes, and original on-wire length.
+    pub fn captured(&self) -> CapturedPacket<'_> {
+        CapturedPacket {
+            secs: self.ts,
+            frac: self.tn,
+            orig_len: self.origlen as usize,
+            data: &self.data,
+        }
+    }
+}
+
+/// A read-only view of a single captured packet, independent of the on-disk
+/// record layout. The `frac` units (micro- or nanoseconds) follow the capture's
+/// [`Encoding::nanos`] flag.
+#[derive(Debug, PartialEq)]
+pub struct CapturedPacket<'a> {
+    /// Seconds portion of the capture timestamp.
+    pub secs: u32,
+    /// Sub-second portion of the capture timestamp.
+    pub frac: u32,
+    /// Length the packet had on the wire before truncation.
+    pub orig_len: usize,
+    /// The captured bytes, which may be shorter than `orig_len`.
+    pub data: &'a [u8],
+}
+
+/// Pad a truncated record back up to its original on-wire length, returning
+/// whether any padding was applied. Records already at full length are left
+/// untouched.
+///
+/// Rejects records whose captured length exceeds their original length, and
+/// caps the padded allocation at `max_len` to protect against hostile length
+/// fields.
+pub fn detruncate(record: &mut PcapRecord, max_len: usize) -> Result<bool, PcapError> {
+    if record.caplen > record.origlen {
+        return Err(PcapError::CaptureExceedsOriginal {
+            caplen: record.caplen,
+            origlen: record.origlen,
+        });
+    }
+    let origlen = record.origlen as usize;
+    if origlen > max_len {
+        return Err(PcapError::RecordTooLarge {
+            len: origlen,
+            limit: max_len,
+        });
+    }
+    if record.caplen == record.origlen {
+        return Ok(false);
+    }
+    record.data.resize(origlen, 0x00);
+    record.caplen = record.origlen;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(caplen: u32, origlen: u32) -> PcapRecord {
+        PcapRecord {
+            ts: 1,
+            tn: 2,
+            caplen,
+            origlen,
+            data: vec![0xAB; caplen as usize],
+        }
+    }
+
+    #[test]
+    fn detruncate_pads_truncated_record() {
+        let mut r = record(96, 1500);
+        assert!(detruncate(&mut r, 1 << 31).unwrap());
+        assert_eq!(r.caplen, 1500);
+        assert_eq!(r.data.len(), 1500);
+        assert_eq!(&r.data[..96], &[0xAB; 96][..]);
+        assert!(r.data[96..].iter().all(|&b| b == 0));
+    }
+
+    #[test]
+    fn detruncate_leaves_full_record() {
+        let mut r = record(200, 200);
+        assert!(!detruncate(&mut r, 1 << 31).unwrap());
+        assert_eq!(r.data.len(), 200);
+    }
+
+    #[test]
+    fn detruncate_rejects_caplen_over_origlen() {
+        let mut r = record(300, 200);
+        assert!(matches!(
+            detruncate(&mut r, 1 << 31),
+            Err(PcapError::CaptureExceedsOriginal { .. })
+        ));
+    }
+
+    #[test]
+    fn detruncate_rejects_over_limit() {
+        let mut r = record(10, 5000);
+        assert!(matches!(
+            detruncate(&mut r, 1024),
+            Err(PcapError::RecordTooLarge { .. })
+        ));
+    }
+}
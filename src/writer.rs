@@ -0,0 +1,26 @@
+//! Streaming libpcap writer.
+
+use std::io::{self, Write};
+
+use crate::record::{Encoding, PcapHeader, PcapRecord};
+
+/// Streaming pcap writer that emits the file header once and then appends each
+/// record as it is produced.
+pub struct PcapWriter<W: Write> {
+    writer: W,
+    enc: Encoding,
+}
+
+impl<W: Write> PcapWriter<W> {
+    /// Write the file header in `enc`'s byte order, returning a writer ready to
+    /// accept records.
+    pub fn new(mut writer: W, header: &PcapHeader, enc: Encoding) -> io::Result<Self> {
+        writer.write_all(&header.out(enc))?;
+        Ok(PcapWriter { writer, enc })
+    }
+
+    /// Append a single record in the capture's byte order.
+    pub fn write_record(&mut self, record: &PcapRecord) -> io::Result<()> {
+        self.writer.write_all(&record.out(self.enc))
+    }
+}